@@ -1,87 +1,362 @@
 extern crate zed_extension_api;
-use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
 
-use zed_extension_api::{self as zed, LanguageServerId};
+use serde::{Deserialize, Serialize};
+use zed::settings::LspSettings;
+use zed::LanguageServerId;
+use zed_extension_api::{self as zed, Result};
 
-struct DesignTokensLanguageserverExtension {
+/// User-facing settings for the design-tokens language server, read from the
+/// Zed `lsp."design-tokens-language-server".settings` block and forwarded to
+/// the server as LSP initialization options / workspace configuration.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DesignTokensSettings {
+    /// DTCG token files the server should load.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tokens_files: Vec<serde_json::Value>,
+    /// Prefix applied to token names when resolving references.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    prefix: Option<String>,
+    /// Group markers used to recognize token groups.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    group_markers: Vec<String>,
+    /// Which GitHub release channel to install from.
+    #[serde(default, skip_serializing)]
+    release_channel: ReleaseChannel,
+    /// Debugging options that never reach the server itself.
+    #[serde(default, skip_serializing)]
+    debug: DesignTokensDebugSettings,
+    /// Extra environment variables to set on the server process (e.g. log levels).
+    #[serde(default, skip_serializing)]
+    env: Vec<(String, String)>,
+}
+
+/// Release channel the extension downloads the server from.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum ReleaseChannel {
+    /// Only stable releases.
+    #[default]
+    Stable,
+    /// Include pre-release/nightly builds.
+    PreRelease,
+}
+
+impl ReleaseChannel {
+    /// Whether `latest_github_release` should consider pre-releases.
+    fn pre_release(self) -> bool {
+        matches!(self, ReleaseChannel::PreRelease)
+    }
+
+    /// Short slug used to key the cache directory per channel.
+    fn slug(self) -> &'static str {
+        match self {
+            ReleaseChannel::Stable => "stable",
+            ReleaseChannel::PreRelease => "pre-release",
+        }
+    }
+}
+
+/// The channel other than `channel`, used when preserving sibling caches.
+fn other_channel(channel: ReleaseChannel) -> ReleaseChannel {
+    match channel {
+        ReleaseChannel::Stable => ReleaseChannel::PreRelease,
+        ReleaseChannel::PreRelease => ReleaseChannel::Stable,
+    }
+}
+
+/// Contributor-facing debugging knobs, surfaced under the `debug` settings key.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DesignTokensDebugSettings {
+    /// Wrap the server in `lsp-devtools agent` to capture JSON-RPC traffic.
+    #[serde(default)]
+    lsp_devtools: bool,
+}
+
+impl DesignTokensSettings {
+    /// Read the server settings from the worktree, falling back to defaults
+    /// when the user has not configured the extension.
+    fn for_worktree(worktree: &zed::Worktree) -> Self {
+        LspSettings::for_worktree("design-tokens-language-server", worktree)
+            .ok()
+            .and_then(|settings| settings.settings)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default()
+    }
+}
+
+struct DesignTokensExtension {
     cached_binary_path: Option<String>,
 }
 
-impl DesignTokensLanguageserverExtension {
-    fn language_server_binary_path(
+/// Map a release asset's file extension to the matching [`zed::DownloadedFileType`].
+///
+/// `.gz` is a single gzipped binary, `.tar.gz`/`.tgz` a gzipped tarball, `.zip`
+/// a zip archive, and anything else is assumed to be an uncompressed binary.
+fn downloaded_file_type(asset_name: &str) -> zed::DownloadedFileType {
+    if asset_name.ends_with(".tar.gz") || asset_name.ends_with(".tgz") {
+        zed::DownloadedFileType::GzipTar
+    } else if asset_name.ends_with(".zip") {
+        zed::DownloadedFileType::Zip
+    } else if asset_name.ends_with(".gz") {
+        zed::DownloadedFileType::Gzip
+    } else {
+        zed::DownloadedFileType::Uncompressed
+    }
+}
+
+/// Strip the compression extension from an asset name so the decompressed file
+/// keeps the binary's own name (e.g. `dtls-linux.gz` -> `dtls-linux`).
+fn stripped_asset_name(asset_name: &str) -> String {
+    for suffix in [".tar.gz", ".tgz", ".zip", ".gz"] {
+        if let Some(stripped) = asset_name.strip_suffix(suffix) {
+            return stripped.to_string();
+        }
+    }
+    asset_name.to_string()
+}
+
+/// Name of the server binary as it appears inside an extracted archive.
+fn binary_file_name(platform: &zed::Os) -> String {
+    match platform {
+        zed::Os::Windows => "design-tokens-language-server.exe".to_string(),
+        _ => "design-tokens-language-server".to_string(),
+    }
+}
+
+/// Recursively search `dir` for the server binary, since archives frequently
+/// nest the executable under a top-level folder rather than placing it at the
+/// archive root.
+fn find_binary_in(dir: &str, binary_name: &str) -> Option<String> {
+    let entries = fs::read_dir(dir).ok()?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let matches = entry
+            .file_name()
+            .to_str()
+            .map_or(false, |name| name == binary_name);
+        if matches && path.is_file() {
+            return path.to_str().map(|p| p.to_string());
+        }
+        if path.is_dir() {
+            if let Some(found) = path.to_str().and_then(|p| find_binary_in(p, binary_name)) {
+                return Some(found);
+            }
+        }
+    }
+    None
+}
+
+impl DesignTokensExtension {
+    fn language_server_binary(
         &mut self,
-        _id: &LanguageServerId,
+        language_server_id: &LanguageServerId,
         worktree: &zed::Worktree,
-    ) -> Result<String, String> {
-        if let Some(path) = Some(&self.get_local_bin_path(worktree)) {
-            return Ok(path.to_string());
+    ) -> Result<String> {
+        // Candidates are tried in precedence order; each is recorded so a
+        // failure can report exactly which sources were probed.
+        let mut tried: Vec<String> = Vec::new();
+
+        // 1. An explicit path configured in the extension settings.
+        if let Ok(settings) = LspSettings::for_worktree("design-tokens-language-server", worktree) {
+            if let Some(path) = settings.binary.and_then(|binary| binary.path) {
+                tried.push(format!("configured binary.path ({path})"));
+                if fs::metadata(&path).map_or(false, |stat| stat.is_file()) {
+                    return Ok(path);
+                }
+            }
         }
 
+        // 2. A server already installed on the user's `$PATH`.
+        tried.push("design-tokens-language-server on $PATH".to_string());
+        if let Some(path) = worktree.which("design-tokens-language-server") {
+            return Ok(path);
+        }
+
+        // 3. A project-local install under `node_modules/.bin`.
+        let node_modules_path = format!(
+            "{}/node_modules/.bin/design-tokens-language-server",
+            worktree.root_path()
+        );
+        tried.push(format!("project-local {node_modules_path}"));
+        if fs::metadata(&node_modules_path).map_or(false, |stat| stat.is_file()) {
+            return Ok(node_modules_path);
+        }
+
+        // 4. A previously cached download.
         if let Some(path) = &self.cached_binary_path {
             if fs::metadata(path).map_or(false, |stat| stat.is_file()) {
                 return Ok(path.clone());
             }
         }
 
-        let result = self.copy_bin(worktree);
-        match result {
-            Ok(path) => {
-                self.cached_binary_path = Some(path.clone());
-                return Ok(path);
-            }
-            Err(err) => Err(err.to_string()),
-        }
+        // 5. Fall back to downloading a release from GitHub.
+        let channel = DesignTokensSettings::for_worktree(worktree).release_channel;
+        tried.push(format!(
+            "cached/downloaded GitHub release ({} channel)",
+            channel.slug()
+        ));
+        self.download_github_release(language_server_id, channel)
+            .map_err(|err| format!("{err} (tried: {})", tried.join(", ")))
     }
 
-    fn get_local_bin_path(&mut self, worktree: &zed::Worktree) -> String {
-        let env = worktree
-            .shell_env()
-            .into_iter()
-            .map(|data| (data.0, data.1))
-            .collect::<HashMap<String, String>>();
-
-        let home = match env.get("HOME") {
-            Some(h) => h,
-            None => {
-                panic!("No HOME env var")
+    fn download_github_release(
+        &mut self,
+        language_server_id: &LanguageServerId,
+        channel: ReleaseChannel,
+    ) -> Result<String> {
+        zed::set_language_server_installation_status(
+            language_server_id,
+            &zed::LanguageServerInstallationStatus::CheckingForUpdate,
+        );
+        let release = zed::latest_github_release(
+            "bennypowers/design-tokens-language-server",
+            zed::GithubReleaseOptions {
+                require_assets: true,
+                pre_release: channel.pre_release(),
+            },
+        )?;
+
+        let (platform, arch) = zed::current_platform();
+        // Binary names for the design tokens language server:
+        //  * - design-tokens-language-server-aarch64-apple-darwin
+        //  * - design-tokens-language-server-aarch64-unknown-linux-gnu
+        //  * - design-tokens-language-server-x86_64-apple-darwin
+        //  * - design-tokens-language-server-x86_64-unknown-linux-gnu
+        //  * - design-tokens-language-server-win-x64.exe
+        //  * - design-tokens-language-server-win-arm64.exe
+        let asset_name = match platform {
+            // Windows uses simplified naming
+            zed::Os::Windows => {
+                let arch_name = match arch {
+                    zed::Architecture::Aarch64 => "arm64",
+                    zed::Architecture::X8664 => "x64",
+                    zed::Architecture::X86 => todo!(),
+                };
+                format!("design-tokens-language-server-win-{}.exe", arch_name)
+            }
+            // Unix platforms use target triples
+            _ => {
+                let arch_name = match arch {
+                    zed::Architecture::Aarch64 => "aarch64",
+                    zed::Architecture::X8664 => "x86_64",
+                    zed::Architecture::X86 => todo!(),
+                };
+                let os_name = match platform {
+                    zed::Os::Mac => "apple-darwin",
+                    zed::Os::Linux => "unknown-linux-gnu",
+                    zed::Os::Windows => unreachable!(),
+                };
+                format!("design-tokens-language-server-{}-{}", arch_name, os_name)
             }
         };
 
-        let state_home = match env.get("XDG_STATE_HOME") {
-            Some(h) => h,
-            None => &Path::new(&home)
-                .join(".local")
-                .to_string_lossy()
-                .to_string(),
+        // The constructed `asset_name` carries no compression suffix, but a
+        // release may ship `….gz`/`.tar.gz`/`.zip` variants. Match on the
+        // stem (the name with any compression suffix and trailing `.exe`
+        // removed) so a compressed asset is found just like a plain one.
+        let wanted_stem = asset_name.strip_suffix(".exe").unwrap_or(&asset_name);
+        let asset = release
+            .assets
+            .iter()
+            .find(|asset| {
+                let decompressed = stripped_asset_name(&asset.name);
+                let stem = decompressed.strip_suffix(".exe").unwrap_or(&decompressed);
+                stem == wanted_stem
+            })
+            .ok_or_else(|| format!("no asset found matching {:?}", asset_name))?;
+
+        // Key the cache directory on channel as well as version so switching
+        // between stable and pre-release keeps both caches around.
+        let version_dir = format!(
+            "design-tokens-language-server-{}-{}",
+            channel.slug(),
+            release.version
+        );
+        fs::create_dir_all(&version_dir)
+            .map_err(|err| format!("failed to create directory '{version_dir}': {err}"))?;
+
+        // Pick the decompression strategy from the *matched asset's* real name
+        // so the release workflow can publish compressed artifacts per platform.
+        let file_type = downloaded_file_type(&asset.name);
+
+        // For archives we download into a directory and resolve the binary
+        // inside it afterwards; plain and gzip assets land on a single file.
+        let is_archive = matches!(
+            file_type,
+            zed::DownloadedFileType::GzipTar | zed::DownloadedFileType::Zip
+        );
+        let binary_name = binary_file_name(&platform);
+        let download_path = if is_archive {
+            format!("{version_dir}/extracted")
+        } else {
+            format!("{version_dir}/{}", stripped_asset_name(&asset.name))
         };
 
-        return Path::new(state_home)
-            .join("bin")
-            .join("design-tokens-language-server")
-            .to_string_lossy()
-            .to_string();
-    }
+        // For single-file downloads the binary path is known up front; for
+        // archives it is only known once the tree is on disk.
+        let cached_binary = if is_archive {
+            find_binary_in(&download_path, &binary_name)
+        } else {
+            Some(download_path.clone())
+        };
 
-    fn copy_bin(&mut self, worktree: &zed::Worktree) -> Result<String, std::io::Error> {
-        let binary_path = Path::new(&worktree.root_path())
-            .join("node_modules/.bin/design-tokens-language-server")
-            .to_string_lossy()
-            .to_string();
+        let binary_path = match cached_binary {
+            Some(path) if fs::metadata(&path).map_or(false, |stat| stat.is_file()) => path,
+            _ => {
+                zed::set_language_server_installation_status(
+                    language_server_id,
+                    &zed::LanguageServerInstallationStatus::Downloading,
+                );
 
-        let local_bin_path = self.get_local_bin_path(worktree);
+                zed::download_file(&asset.download_url, &download_path, file_type)
+                    .map_err(|err| format!("failed to download file: {err}"))?;
 
-        let result = fs::copy(&local_bin_path, binary_path.clone());
+                // Archives commonly nest the executable under a top-level
+                // folder, so locate it rather than assuming a flat layout.
+                let binary_path = if is_archive {
+                    find_binary_in(&download_path, &binary_name).ok_or_else(|| {
+                        format!("no {binary_name:?} found under {download_path:?}")
+                    })?
+                } else {
+                    download_path.clone()
+                };
 
-        match result {
-            Ok(_u64) => Ok(binary_path),
-            Err(e) => Err(e),
-        }
+                zed::make_file_executable(&binary_path)?;
+
+                // Preserve the freshly downloaded directory and any cached dir
+                // for the sibling channel (regardless of version), so flipping
+                // channels back and forth doesn't force a redownload.
+                let sibling_prefix = format!(
+                    "design-tokens-language-server-{}-",
+                    other_channel(channel).slug()
+                );
+                let entries = fs::read_dir(".")
+                    .map_err(|err| format!("failed to list working directory {err}"))?;
+                for entry in entries {
+                    let entry =
+                        entry.map_err(|err| format!("failed to load directory entry {err}"))?;
+                    let name = entry.file_name();
+                    let name = name.to_str();
+                    let keep = name == Some(&version_dir)
+                        || name.map_or(false, |n| n.starts_with(&sibling_prefix));
+                    if !keep {
+                        fs::remove_dir_all(entry.path()).ok();
+                    }
+                }
+
+                binary_path
+            }
+        };
+
+        self.cached_binary_path = Some(binary_path.clone());
+        Ok(binary_path)
     }
 }
 
-impl zed::Extension for DesignTokensLanguageserverExtension {
+impl zed::Extension for DesignTokensExtension {
     fn new() -> Self {
         Self {
             cached_binary_path: None,
@@ -90,19 +365,52 @@ impl zed::Extension for DesignTokensLanguageserverExtension {
 
     fn language_server_command(
         &mut self,
-        id: &LanguageServerId,
+        language_server_id: &LanguageServerId,
         worktree: &zed::Worktree,
-    ) -> Result<zed::Command, std::string::String> {
-        let command = self.language_server_binary_path(id, worktree);
-        match command {
-            Ok(command) => Ok(zed::Command {
-                command: command.to_string(),
-                args: [].to_vec(),
-                env: Default::default(),
-            }),
-            Err(err) => Err(err),
+    ) -> Result<zed::Command> {
+        let dtls_binary = self.language_server_binary(language_server_id, worktree)?;
+        let settings = DesignTokensSettings::for_worktree(worktree);
+
+        // Optionally wrap the server in `lsp-devtools agent -- <dtls_binary>` so
+        // contributors can inspect the JSON-RPC traffic from inside Zed.
+        if settings.debug.lsp_devtools {
+            if let Some(lsp_devtools) = worktree.which("lsp-devtools") {
+                return Ok(zed::Command {
+                    command: lsp_devtools,
+                    args: vec!["agent".to_string(), "--".to_string(), dtls_binary],
+                    env: settings.env,
+                });
+            }
         }
+
+        Ok(zed::Command {
+            command: dtls_binary,
+            args: vec![],
+            env: settings.env,
+        })
+    }
+
+    fn language_server_initialization_options(
+        &mut self,
+        _language_server_id: &LanguageServerId,
+        worktree: &zed::Worktree,
+    ) -> Result<Option<serde_json::Value>> {
+        let settings = DesignTokensSettings::for_worktree(worktree);
+        Ok(Some(serde_json::to_value(settings).map_err(|err| {
+            format!("failed to serialize initialization options: {err}")
+        })?))
+    }
+
+    fn language_server_workspace_configuration(
+        &mut self,
+        _language_server_id: &LanguageServerId,
+        worktree: &zed::Worktree,
+    ) -> Result<Option<serde_json::Value>> {
+        let settings = DesignTokensSettings::for_worktree(worktree);
+        Ok(Some(serde_json::to_value(settings).map_err(|err| {
+            format!("failed to serialize workspace configuration: {err}")
+        })?))
     }
 }
 
-zed::register_extension!(DesignTokensLanguageserverExtension);
+zed::register_extension!(DesignTokensExtension);